@@ -0,0 +1,168 @@
+use opentelemetry_sdk::trace::Sampler;
+use std::time::Duration;
+
+/// Which OTLP wire protocol to export spans, metrics and logs over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    Grpc,
+    Http,
+}
+
+impl OtlpProtocol {
+    fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("OTLP_PROTOCOL") {
+            Ok(value) => match value.as_str() {
+                "grpc" => Ok(Self::Grpc),
+                "http" => Ok(Self::Http),
+                other => Err(anyhow::anyhow!(
+                    "invalid OTLP_PROTOCOL '{other}', expected 'grpc' or 'http'"
+                )),
+            },
+            Err(_) => Ok(Self::Grpc),
+        }
+    }
+
+    pub fn default_endpoint(self) -> &'static str {
+        match self {
+            Self::Grpc => "http://localhost:4317",
+            Self::Http => "http://localhost:4318",
+        }
+    }
+}
+
+/// Sampling strategy for the tracer provider.
+#[derive(Clone, Copy, Debug)]
+pub enum SamplerConfig {
+    AlwaysOn,
+    AlwaysOff,
+    TraceIdRatio(f64),
+}
+
+impl SamplerConfig {
+    fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("OTLP_SAMPLER") {
+            Ok(value) => match value.as_str() {
+                "always_on" => Ok(Self::AlwaysOn),
+                "always_off" => Ok(Self::AlwaysOff),
+                "traceid_ratio" => {
+                    let ratio = parse_env("OTLP_SAMPLER_RATIO", 1.0)?;
+                    if !(0.0..=1.0).contains(&ratio) {
+                        return Err(anyhow::anyhow!(
+                            "invalid OTLP_SAMPLER_RATIO '{ratio}', expected a value in 0.0..=1.0"
+                        ));
+                    }
+                    Ok(Self::TraceIdRatio(ratio))
+                }
+                other => Err(anyhow::anyhow!(
+                    "invalid OTLP_SAMPLER '{other}', expected 'always_on', 'always_off' or 'traceid_ratio'"
+                )),
+            },
+            Err(_) => Ok(Self::AlwaysOn),
+        }
+    }
+
+    pub fn to_sdk_sampler(self) -> Sampler {
+        match self {
+            Self::AlwaysOn => Sampler::AlwaysOn,
+            Self::AlwaysOff => Sampler::AlwaysOff,
+            Self::TraceIdRatio(ratio) => Sampler::TraceIdRatioBased(ratio),
+        }
+    }
+}
+
+/// Typed, centralized telemetry configuration, read once at startup from
+/// the environment. Replaces the ad-hoc `std::env::var` reads that used to
+/// be scattered across `init_telemetry` and `main`.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub traces_enabled: bool,
+    pub metrics_enabled: bool,
+    pub logs_enabled: bool,
+    pub profiling_enabled: bool,
+    pub otlp_protocol: OtlpProtocol,
+    pub otlp_endpoint: String,
+    pub sampler: SamplerConfig,
+    pub metric_export_interval: Duration,
+    pub pyroscope_url: String,
+    pub pyroscope_sample_rate: u32,
+    pub pyroscope_tags: Vec<(String, String)>,
+}
+
+impl Config {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let otlp_protocol = OtlpProtocol::from_env()?;
+        let otlp_endpoint = std::env::var("OTLP_ENDPOINT")
+            .unwrap_or_else(|_| otlp_protocol.default_endpoint().to_string());
+
+        Ok(Self {
+            traces_enabled: bool_env("TRACES_ENABLED", true)?,
+            metrics_enabled: bool_env("METRICS_ENABLED", true)?,
+            logs_enabled: bool_env("LOGS_ENABLED", true)?,
+            profiling_enabled: bool_env("PROFILING_ENABLED", true)?,
+            otlp_protocol,
+            otlp_endpoint,
+            sampler: SamplerConfig::from_env()?,
+            metric_export_interval: Duration::from_secs(parse_env(
+                "METRIC_EXPORT_INTERVAL_SECS",
+                10,
+            )?),
+            pyroscope_url: std::env::var("PYROSCOPE_URL")
+                .unwrap_or_else(|_| "http://localhost:4040".to_string()),
+            pyroscope_sample_rate: parse_env("PYROSCOPE_SAMPLE_RATE", 100)?,
+            pyroscope_tags: pyroscope_tags_from_env()?,
+        })
+    }
+}
+
+/// Default Pyroscope tags, overlaid with any pairs from `PYROSCOPE_TAGS`
+/// (format: `key=value,key2=value2`). Tags from the environment override
+/// defaults of the same key.
+fn pyroscope_tags_from_env() -> anyhow::Result<Vec<(String, String)>> {
+    let mut tags = vec![
+        ("service".to_string(), "rust-observability-demo".to_string()),
+        ("environment".to_string(), "development".to_string()),
+    ];
+
+    if let Ok(value) = std::env::var("PYROSCOPE_TAGS") {
+        for pair in value.split(',').filter(|s| !s.is_empty()) {
+            let (key, tag_value) = pair.trim().split_once('=').ok_or_else(|| {
+                anyhow::anyhow!("invalid PYROSCOPE_TAGS entry '{pair}', expected 'key=value'")
+            })?;
+            let key = key.trim().to_string();
+            let tag_value = tag_value.trim().to_string();
+
+            if let Some(existing) = tags.iter_mut().find(|(k, _)| *k == key) {
+                existing.1 = tag_value;
+            } else {
+                tags.push((key, tag_value));
+            }
+        }
+    }
+
+    Ok(tags)
+}
+
+fn bool_env(name: &str, default: bool) -> anyhow::Result<bool> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse::<bool>()
+            .map_err(|_| anyhow::anyhow!("invalid {name} '{value}', expected 'true' or 'false'")),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Parses a numeric env var, hard-erroring on a malformed value rather than
+/// silently falling back to `default` (matches the strict `bool_env`/
+/// `OtlpProtocol`/`SamplerConfig` handling).
+fn parse_env<T>(name: &str, default: T) -> anyhow::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|e| anyhow::anyhow!("invalid {name} '{value}': {e}")),
+        Err(_) => Ok(default),
+    }
+}
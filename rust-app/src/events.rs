@@ -0,0 +1,44 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// A single traffic event as recorded by the instrumentation layer, shaped
+/// for the `/events` SSE route rather than for Prometheus-style labels.
+#[derive(Clone, Serialize)]
+pub struct RequestEvent {
+    pub endpoint: String,
+    pub method: String,
+    pub status: u16,
+    pub duration_ms: f64,
+}
+
+/// Zero-dependency pub-sub: the instrumentation layer publishes a
+/// `RequestEvent` per request, and the `/events` SSE route subscribes to
+/// give a live tail of traffic without round-tripping through Grafana.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<RequestEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: RequestEvent) {
+        // Err just means nobody is currently subscribed to /events; that's fine.
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RequestEvent> {
+        self.sender.subscribe()
+    }
+}
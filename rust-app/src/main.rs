@@ -1,23 +1,38 @@
+mod config;
+mod events;
+mod metrics;
+mod middleware;
+
 use axum::{
     extract::Path,
-    response::{Html, IntoResponse},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse,
+    },
     routing::{get, post},
     Json, Router,
 };
+use config::{Config, OtlpProtocol};
+use futures_util::{Stream, StreamExt};
+use metrics::{AppMetrics, MetricsLayer};
+use middleware::RequestIdLayer;
 use opentelemetry::{
     global,
-    metrics::{Counter, Histogram},
     trace::{Span, Tracer, TracerProvider as _},
     KeyValue,
 };
 use opentelemetry_otlp::WithExportConfig;
 use opentelemetry_sdk::{
     metrics::{PeriodicReader, SdkMeterProvider},
-    trace::{RandomIdGenerator, Sampler, SdkTracerProvider},
+    propagation::TraceContextPropagator,
+    trace::{RandomIdGenerator, SdkTracerProvider},
     Resource,
 };
 use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::SocketAddr;
 use std::time::Duration;
+use tokio_stream::wrappers::BroadcastStream;
 use tower_http::trace::TraceLayer;
 use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -25,34 +40,6 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use pyroscope::PyroscopeAgent;
 use pyroscope_pprofrs::{pprof_backend, PprofConfig};
 
-#[derive(Clone)]
-struct AppMetrics {
-    request_counter: Counter<u64>,
-    request_duration: Histogram<f64>,
-    error_counter: Counter<u64>,
-}
-
-impl AppMetrics {
-    fn new() -> Self {
-        let meter = global::meter("observability-demo");
-
-        Self {
-            request_counter: meter
-                .u64_counter("http_requests_total")
-                .with_description("Total number of HTTP requests")
-                .build(),
-            request_duration: meter
-                .f64_histogram("http_request_duration_seconds")
-                .with_description("HTTP request duration in seconds")
-                .build(),
-            error_counter: meter
-                .u64_counter("errors_total")
-                .with_description("Total number of errors")
-                .build(),
-        }
-    }
-}
-
 #[derive(Serialize, Deserialize)]
 struct CalculateRequest {
     a: f64,
@@ -67,26 +54,38 @@ struct CalculateResponse {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    init_telemetry()?;
-
-    info!("Starting observability demo application");
+    let config = Config::from_env()?;
 
-    let pyroscope_url = std::env::var("PYROSCOPE_URL")
-        .unwrap_or_else(|_| "http://localhost:4040".to_string());
+    init_telemetry(&config)?;
 
-    let app_name = "rust-observability-demo".to_string();
-
-    let agent = PyroscopeAgent::builder(&pyroscope_url, &app_name)
-        .tags(vec![
-            ("service", "rust-observability-demo"),
-            ("environment", "development"),
-        ])
-        .backend(pprof_backend(PprofConfig::new().sample_rate(100)))
-        .build()
-        .map_err(|e| anyhow::anyhow!("Failed to initialize Pyroscope: {}", e))?;
+    info!("Starting observability demo application");
 
-    let agent_running = agent.start().map_err(|e| anyhow::anyhow!("Failed to start Pyroscope agent: {}", e))?;
-    info!("Pyroscope continuous profiling started");
+    let agent_running = if config.profiling_enabled {
+        let app_name = "rust-observability-demo".to_string();
+
+        let tags = config
+            .pyroscope_tags
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+
+        let agent = PyroscopeAgent::builder(&config.pyroscope_url, &app_name)
+            .tags(tags)
+            .backend(pprof_backend(
+                PprofConfig::new().sample_rate(config.pyroscope_sample_rate),
+            ))
+            .build()
+            .map_err(|e| anyhow::anyhow!("Failed to initialize Pyroscope: {}", e))?;
+
+        let agent_running = agent
+            .start()
+            .map_err(|e| anyhow::anyhow!("Failed to start Pyroscope agent: {}", e))?;
+        info!("Pyroscope continuous profiling started");
+
+        Some(agent_running)
+    } else {
+        None
+    };
 
     let metrics = AppMetrics::new();
 
@@ -98,25 +97,57 @@ async fn main() -> anyhow::Result<()> {
         .route("/simulate/slow", get(slow_handler))
         .route("/simulate/error", get(error_handler))
         .route("/user/:id", get(user_handler))
+        .route("/events", get(events_handler))
         .layer(TraceLayer::new_for_http())
+        .layer(MetricsLayer::new(metrics.clone()))
+        .layer(RequestIdLayer)
         .with_state(metrics);
 
     let addr = "0.0.0.0:8080";
     info!("Server listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
-    let agent_ready = agent_running.stop()?;
-    agent_ready.shutdown();
-    info!("Pyroscope profiling stopped");
+    if let Some(agent_running) = agent_running {
+        let agent_ready = agent_running.stop()?;
+        agent_ready.shutdown();
+        info!("Pyroscope profiling stopped");
+    }
 
     Ok(())
 }
 
-fn init_telemetry() -> anyhow::Result<()> {
-    let otlp_endpoint = std::env::var("OTLP_ENDPOINT")
-        .unwrap_or_else(|_| "http://localhost:4317".to_string());
+/// Applies the configured transport, endpoint and timeout to an OTLP
+/// exporter builder and builds it. `with_tonic`/`with_http` return
+/// different concrete builder types, so each arm has to finish the chain
+/// itself, but this keeps the endpoint/timeout/build wiring in one place
+/// instead of repeating it at every one of the span/metric/log call sites.
+macro_rules! configure_otlp_exporter {
+    ($builder:expr, $protocol:expr, $endpoint:expr) => {
+        match $protocol {
+            OtlpProtocol::Grpc => $builder
+                .with_tonic()
+                .with_endpoint($endpoint)
+                .with_timeout(Duration::from_secs(3))
+                .build(),
+            OtlpProtocol::Http => $builder
+                .with_http()
+                .with_endpoint($endpoint)
+                .with_timeout(Duration::from_secs(3))
+                .build(),
+        }
+    };
+}
+
+fn init_telemetry(config: &Config) -> anyhow::Result<()> {
+    // Register the W3C propagator globally so incoming `traceparent`/
+    // `tracestate` headers are honored and outgoing calls carry them too.
+    global::set_text_map_propagator(TraceContextPropagator::new());
 
     let resource = Resource::builder()
         .with_service_name("rust-observability-demo")
@@ -124,55 +155,67 @@ fn init_telemetry() -> anyhow::Result<()> {
         .with_attribute(KeyValue::new("deployment.environment", "development"))
         .build();
 
-    let tracer_provider = SdkTracerProvider::builder()
-        .with_batch_exporter(
-            opentelemetry_otlp::SpanExporter::builder()
-                .with_tonic()
-                .with_endpoint(&otlp_endpoint)
-                .with_timeout(Duration::from_secs(3))
-                .build()?,
-        )
-        .with_resource(resource.clone())
-        .with_id_generator(RandomIdGenerator::default())
-        .with_sampler(Sampler::AlwaysOn)
-        .build();
-
-    global::set_tracer_provider(tracer_provider.clone());
-
-    let exporter = opentelemetry_otlp::MetricExporter::builder()
-        .with_tonic()
-        .with_endpoint(&otlp_endpoint)
-        .with_timeout(Duration::from_secs(3))
-        .build()?;
-
-    let reader = PeriodicReader::builder(exporter)
-        .with_interval(Duration::from_secs(10))
-        .build();
-
-    let meter_provider = SdkMeterProvider::builder()
-        .with_resource(resource.clone())
-        .with_reader(reader)
-        .build();
-
-    global::set_meter_provider(meter_provider);
-
-    let log_exporter = opentelemetry_otlp::LogExporter::builder()
-        .with_tonic()
-        .with_endpoint(&otlp_endpoint)
-        .with_timeout(Duration::from_secs(3))
-        .build()?;
+    let tracer_provider = if config.traces_enabled {
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(configure_otlp_exporter!(
+                opentelemetry_otlp::SpanExporter::builder(),
+                config.otlp_protocol,
+                &config.otlp_endpoint
+            )?)
+            .with_resource(resource.clone())
+            .with_id_generator(RandomIdGenerator::default())
+            .with_sampler(config.sampler.to_sdk_sampler())
+            .build();
+
+        global::set_tracer_provider(provider.clone());
+        Some(provider)
+    } else {
+        None
+    };
+
+    if config.metrics_enabled {
+        let exporter = configure_otlp_exporter!(
+            opentelemetry_otlp::MetricExporter::builder(),
+            config.otlp_protocol,
+            &config.otlp_endpoint
+        )?;
+
+        let reader = PeriodicReader::builder(exporter)
+            .with_interval(config.metric_export_interval)
+            .build();
+
+        let meter_provider = SdkMeterProvider::builder()
+            .with_resource(resource.clone())
+            .with_reader(reader)
+            .build();
+
+        global::set_meter_provider(meter_provider);
+    }
 
-    let logger_provider = opentelemetry_sdk::logs::SdkLoggerProvider::builder()
-        .with_batch_exporter(log_exporter)
-        .with_resource(resource.clone())
-        .build();
+    let logger_provider = if config.logs_enabled {
+        let log_exporter = configure_otlp_exporter!(
+            opentelemetry_otlp::LogExporter::builder(),
+            config.otlp_protocol,
+            &config.otlp_endpoint
+        )?;
+
+        Some(
+            opentelemetry_sdk::logs::SdkLoggerProvider::builder()
+                .with_batch_exporter(log_exporter)
+                .with_resource(resource.clone())
+                .build(),
+        )
+    } else {
+        None
+    };
 
-    let telemetry_layer = tracing_opentelemetry::layer()
-        .with_tracer(tracer_provider.tracer("observability-demo"));
+    let telemetry_layer = tracer_provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("observability-demo"))
+    });
 
-    let otel_log_layer = opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge::new(
-        &logger_provider
-    );
+    let otel_log_layer = logger_provider.as_ref().map(|provider| {
+        opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge::new(provider)
+    });
 
     tracing_subscriber::registry()
         .with(
@@ -206,28 +249,16 @@ async fn root_handler() -> Html<&'static str> {
             <li>GET /simulate/slow - Simulate slow request</li>
             <li>GET /simulate/error - Simulate error</li>
             <li>GET /user/:id - Get user by ID</li>
+            <li>GET /events - Live SSE tail of traffic</li>
         </ul>
         "#,
     )
 }
 
 #[axum::debug_handler]
-async fn health_handler(axum::extract::State(metrics): axum::extract::State<AppMetrics>) -> impl IntoResponse {
-    let start = std::time::Instant::now();
-
+async fn health_handler() -> impl IntoResponse {
     info!("Health check requested");
 
-    metrics.request_counter.add(
-        1,
-        &[KeyValue::new("endpoint", "/health"), KeyValue::new("method", "GET")],
-    );
-
-    let duration = start.elapsed().as_secs_f64();
-    metrics.request_duration.record(
-        duration,
-        &[KeyValue::new("endpoint", "/health")],
-    );
-
     Json(serde_json::json!({
         "status": "healthy",
         "service": "rust-observability-demo"
@@ -235,11 +266,7 @@ async fn health_handler(axum::extract::State(metrics): axum::extract::State<AppM
 }
 
 #[axum::debug_handler]
-async fn add_handler(
-    axum::extract::State(metrics): axum::extract::State<AppMetrics>,
-    Json(payload): Json<CalculateRequest>,
-) -> impl IntoResponse {
-    let start = std::time::Instant::now();
+async fn add_handler(Json(payload): Json<CalculateRequest>) -> impl IntoResponse {
     let tracer = global::tracer("observability-demo");
     let mut span = tracer.start("calculate_add");
 
@@ -252,17 +279,6 @@ async fn add_handler(
     let result = payload.a + payload.b;
     span.set_attribute(KeyValue::new("result", result));
 
-    metrics.request_counter.add(
-        1,
-        &[KeyValue::new("endpoint", "/calculate/add"), KeyValue::new("method", "POST")],
-    );
-
-    let duration = start.elapsed().as_secs_f64();
-    metrics.request_duration.record(
-        duration,
-        &[KeyValue::new("endpoint", "/calculate/add")],
-    );
-
     span.end();
 
     Json(CalculateResponse {
@@ -273,10 +289,8 @@ async fn add_handler(
 
 #[axum::debug_handler]
 async fn divide_handler(
-    axum::extract::State(metrics): axum::extract::State<AppMetrics>,
     Json(payload): Json<CalculateRequest>,
 ) -> Result<Json<CalculateResponse>, (axum::http::StatusCode, String)> {
-    let start = std::time::Instant::now();
     let tracer = global::tracer("observability-demo");
     let mut span = tracer.start("calculate_divide");
 
@@ -291,11 +305,6 @@ async fn divide_handler(
         span.set_attribute(KeyValue::new("error", true));
         span.set_attribute(KeyValue::new("error.message", "division by zero"));
 
-        metrics.error_counter.add(
-            1,
-            &[KeyValue::new("error_type", "division_by_zero")],
-        );
-
         span.end();
         return Err((
             axum::http::StatusCode::BAD_REQUEST,
@@ -306,17 +315,6 @@ async fn divide_handler(
     let result = payload.a / payload.b;
     span.set_attribute(KeyValue::new("result", result));
 
-    metrics.request_counter.add(
-        1,
-        &[KeyValue::new("endpoint", "/calculate/divide"), KeyValue::new("method", "POST")],
-    );
-
-    let duration = start.elapsed().as_secs_f64();
-    metrics.request_duration.record(
-        duration,
-        &[KeyValue::new("endpoint", "/calculate/divide")],
-    );
-
     span.end();
 
     Ok(Json(CalculateResponse {
@@ -326,8 +324,7 @@ async fn divide_handler(
 }
 
 #[axum::debug_handler]
-async fn slow_handler(axum::extract::State(metrics): axum::extract::State<AppMetrics>) -> impl IntoResponse {
-    let start = std::time::Instant::now();
+async fn slow_handler() -> impl IntoResponse {
     let tracer = global::tracer("observability-demo");
     let mut span = tracer.start("slow_operation");
 
@@ -335,29 +332,15 @@ async fn slow_handler(axum::extract::State(metrics): axum::extract::State<AppMet
 
     tokio::time::sleep(Duration::from_secs(2)).await;
 
-    metrics.request_counter.add(
-        1,
-        &[KeyValue::new("endpoint", "/simulate/slow"), KeyValue::new("method", "GET")],
-    );
-
-    let duration = start.elapsed().as_secs_f64();
-    metrics.request_duration.record(
-        duration,
-        &[KeyValue::new("endpoint", "/simulate/slow")],
-    );
-
     span.end();
 
     Json(serde_json::json!({
-        "message": "Slow operation completed",
-        "duration_seconds": duration
+        "message": "Slow operation completed"
     }))
 }
 
 #[axum::debug_handler]
-async fn error_handler(
-    axum::extract::State(metrics): axum::extract::State<AppMetrics>,
-) -> Result<(), (axum::http::StatusCode, String)> {
+async fn error_handler() -> Result<(), (axum::http::StatusCode, String)> {
     let tracer = global::tracer("observability-demo");
     let mut span = tracer.start("error_operation");
 
@@ -366,11 +349,6 @@ async fn error_handler(
     span.set_attribute(KeyValue::new("error", true));
     span.set_attribute(KeyValue::new("error.message", "simulated error"));
 
-    metrics.error_counter.add(
-        1,
-        &[KeyValue::new("error_type", "simulated")],
-    );
-
     span.end();
 
     Err((
@@ -380,11 +358,7 @@ async fn error_handler(
 }
 
 #[axum::debug_handler]
-async fn user_handler(
-    axum::extract::State(metrics): axum::extract::State<AppMetrics>,
-    Path(user_id): Path<u64>,
-) -> impl IntoResponse {
-    let start = std::time::Instant::now();
+async fn user_handler(Path(user_id): Path<u64>) -> impl IntoResponse {
     let tracer = global::tracer("observability-demo");
     let mut span = tracer.start("get_user");
 
@@ -395,17 +369,6 @@ async fn user_handler(
     // Simulate database lookup
     tokio::time::sleep(Duration::from_millis(100)).await;
 
-    metrics.request_counter.add(
-        1,
-        &[KeyValue::new("endpoint", "/user/:id"), KeyValue::new("method", "GET")],
-    );
-
-    let duration = start.elapsed().as_secs_f64();
-    metrics.request_duration.record(
-        duration,
-        &[KeyValue::new("endpoint", "/user/:id")],
-    );
-
     span.end();
 
     Json(serde_json::json!({
@@ -414,3 +377,21 @@ async fn user_handler(
         "email": format!("user{}@example.com", user_id)
     }))
 }
+
+/// Zero-dependency live tail of traffic for local debugging: streams a JSON
+/// event for every request the `MetricsLayer` records, without round-tripping
+/// through Grafana.
+#[axum::debug_handler]
+async fn events_handler(
+    axum::extract::State(metrics): axum::extract::State<AppMetrics>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    info!("SSE client subscribed to /events");
+
+    let stream = BroadcastStream::new(metrics.subscribe_events()).filter_map(|event| async move {
+        let event = event.ok()?;
+        let json = serde_json::to_string(&event).ok()?;
+        Some(Ok(Event::default().data(json)))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,180 @@
+use crate::events::{EventBus, RequestEvent};
+use axum::{
+    extract::{MatchedPath, Request},
+    response::Response,
+};
+use futures_util::future::BoxFuture;
+use opentelemetry::{global, metrics::Counter, metrics::Histogram, KeyValue};
+use std::{
+    task::{Context, Poll},
+    time::Instant,
+};
+use tokio::sync::broadcast;
+use tower::{Layer, Service};
+use tracing::{info_span, Instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+#[derive(Clone)]
+pub struct AppMetrics {
+    request_counter: Counter<u64>,
+    request_duration: Histogram<f64>,
+    error_counter: Counter<u64>,
+    events: EventBus,
+}
+
+impl Default for AppMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppMetrics {
+    pub fn new() -> Self {
+        let meter = global::meter("observability-demo");
+
+        Self {
+            request_counter: meter
+                .u64_counter("http_requests_total")
+                .with_description("Total number of HTTP requests")
+                .build(),
+            request_duration: meter
+                .f64_histogram("http_request_duration_seconds")
+                .with_description("HTTP request duration in seconds")
+                .build(),
+            error_counter: meter
+                .u64_counter("errors_total")
+                .with_description("Total number of errors")
+                .build(),
+            events: EventBus::new(),
+        }
+    }
+
+    /// Subscribes to the live traffic feed backing the `/events` SSE route.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<RequestEvent> {
+        self.events.subscribe()
+    }
+
+    fn record_request(&self, endpoint: &str, method: &str, duration: f64) {
+        self.request_counter.add(
+            1,
+            &[
+                KeyValue::new("endpoint", endpoint.to_string()),
+                KeyValue::new("method", method.to_string()),
+            ],
+        );
+
+        self.record_duration_with_exemplar(endpoint, duration);
+    }
+
+    /// Records a duration on `http_request_duration_seconds` with the
+    /// active span attached as the ambient OpenTelemetry context, so the
+    /// SDK's exemplar filter can attach that span's trace id/span id to the
+    /// measurement. Unsampled spans are filtered out by the SDK itself, so
+    /// only sampled requests end up as exemplars.
+    fn record_duration_with_exemplar(&self, endpoint: &str, duration: f64) {
+        let cx = tracing::Span::current().context();
+        let _attached = cx.attach();
+
+        self.request_duration.record(
+            duration,
+            &[KeyValue::new("endpoint", endpoint.to_string())],
+        );
+    }
+
+    fn record_error(&self, endpoint: &str, status: u16) {
+        self.error_counter.add(
+            1,
+            &[
+                KeyValue::new("endpoint", endpoint.to_string()),
+                KeyValue::new("status", status as i64),
+            ],
+        );
+    }
+}
+
+/// `tower::Layer` that wraps a whole `Router` so every route gets request
+/// counting, duration histograms and error counting for free, instead of
+/// each handler repeating the same bookkeeping.
+#[derive(Clone)]
+pub struct MetricsLayer {
+    metrics: AppMetrics,
+}
+
+impl MetricsLayer {
+    pub fn new(metrics: AppMetrics) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+    metrics: AppMetrics,
+}
+
+impl<S> Service<Request> for MetricsService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let endpoint = req
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|matched_path| matched_path.as_str().to_string())
+            .unwrap_or_else(|| req.uri().path().to_string());
+        let method = req.method().to_string();
+        let metrics = self.metrics.clone();
+
+        // `poll_ready` was called on `self.inner`, not on a fresh clone, so
+        // leave the clone in `self` and drive the ready original in this future.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        let span = info_span!("http_request", %method, %endpoint);
+
+        Box::pin(
+            async move {
+                let start = Instant::now();
+                let response = inner.call(req).await?;
+                let duration = start.elapsed().as_secs_f64();
+
+                metrics.record_request(&endpoint, &method, duration);
+
+                let status = response.status();
+                if status.is_client_error() || status.is_server_error() {
+                    metrics.record_error(&endpoint, status.as_u16());
+                }
+
+                metrics.events.publish(RequestEvent {
+                    endpoint: endpoint.clone(),
+                    method: method.clone(),
+                    status: status.as_u16(),
+                    duration_ms: duration * 1000.0,
+                });
+
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
@@ -0,0 +1,116 @@
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{HeaderMap, HeaderValue},
+    response::Response,
+};
+use futures_util::future::BoxFuture;
+use opentelemetry::global;
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use std::{
+    net::SocketAddr,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+use tracing::{info_span, Instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Access-log style `tower::Layer` that assigns every request a correlation
+/// id (honoring an incoming `x-request-id`), opens a `tracing` span carrying
+/// it alongside the client address, method and path, and echoes the id back
+/// as a response header so Loki lines and Tempo traces can be joined up.
+#[derive(Clone, Copy, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for RequestIdService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        let client_ip = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+
+        let span = info_span!(
+            "request",
+            request_id = %request_id,
+            http.client_ip = %client_ip,
+            %method,
+            %path,
+        );
+
+        // Honor an incoming `traceparent`/`tracestate` so this span becomes
+        // a child of the caller's span instead of a disconnected root.
+        let parent_cx =
+            global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(req.headers())));
+        span.set_parent(parent_cx);
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(
+            async move {
+                let mut response = inner.call(req).await?;
+
+                if let Ok(header_value) = HeaderValue::from_str(&request_id) {
+                    response
+                        .headers_mut()
+                        .insert(REQUEST_ID_HEADER, header_value);
+                }
+
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Injects the current span's OpenTelemetry context into outgoing request
+/// headers, so a downstream HTTP call made from inside a request span
+/// becomes a child of it in Tempo rather than starting a new trace.
+///
+/// Nothing in this demo makes downstream HTTP calls yet, so this has no
+/// caller today; it's the call a future client integration should reach for.
+#[allow(dead_code)]
+pub fn inject_trace_context(headers: &mut HeaderMap) {
+    let cx = tracing::Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&cx, &mut HeaderInjector(headers));
+    });
+}